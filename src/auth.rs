@@ -1,16 +1,24 @@
 
 
-use axum::{extract::FromRequestParts};
+use axum::extract::FromRequestParts;
 use http::request::Parts;
 use serde::{Deserialize, Serialize};
 use tower_sessions::{Session};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use thiserror::Error;
+use std::sync::Arc;
+use uuid::Uuid;
+use crate::store::Store;
 
 const COUNTER_KEY: &str = "counter";
 
+/// Counts requests made within the caller's session. Handlers that accept
+/// this as an extractor get the count tracked as a side effect of
+/// extraction; it doesn't itself reject over-limit requests.
 #[derive(Default, Deserialize, Serialize)]
-struct Counter(usize);
+pub struct RateLimit(usize);
 
-impl<S> FromRequestParts<S> for Counter
+impl<S> FromRequestParts<S> for RateLimit
 where
     S: Send + Sync,
 {
@@ -18,9 +26,172 @@ where
 
     async fn from_request_parts(req: &mut Parts, state: &S) -> Result<Self, Self::Rejection> {
         let session = Session::from_request_parts(req, state).await?;
-        let counter: Counter = session.get(COUNTER_KEY).await.unwrap().unwrap_or_default();
+        let counter: RateLimit = session.get(COUNTER_KEY).await.unwrap().unwrap_or_default();
         session.insert(COUNTER_KEY, counter.0 + 1).await.unwrap();
         Ok(counter)
     }
 }
 
+/// JWT access tokens are short-lived so a stolen one has a small blast radius.
+const ACCESS_TOKEN_TTL_SECONDS: i64 = 15 * 60;
+/// Refresh tokens live much longer; they're only ever exchanged for an access token.
+const REFRESH_TOKEN_TTL_SECONDS: i64 = 60 * 60 * 24 * 30;
+
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenType {
+    Access,
+    Refresh,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct Claims {
+    /// The authenticated user's email.
+    pub sub: String,
+    pub iat: i64,
+    pub exp: i64,
+    pub token_type: TokenType,
+    /// Unique id for this token. For refresh tokens, this is the indexable
+    /// lookup value `is_refresh_token_active` queries by, so checking
+    /// whether a token is still active doesn't require scanning and
+    /// verifying every refresh token ever issued to the user.
+    pub jti: String,
+}
+
+pub struct TokenPair {
+    pub access_token: String,
+    pub refresh_token: String,
+    pub refresh_jti: String,
+}
+
+fn encode_claims(secret: &[u8], email: &str, token_type: TokenType, ttl_seconds: i64, jti: &str) -> Result<String, AuthError> {
+    let now = chrono::Utc::now().timestamp();
+    let claims = Claims {
+        sub: email.to_string(),
+        iat: now,
+        exp: now + ttl_seconds,
+        token_type,
+        jti: jti.to_string(),
+    };
+
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(secret))
+        .map_err(|_| AuthError::TokenCreation)
+}
+
+/// Mints a fresh access/refresh pair for a user who just authenticated.
+pub fn issue_token_pair(secret: &[u8], email: &str) -> Result<TokenPair, AuthError> {
+    let refresh_jti = Uuid::new_v4().to_string();
+    Ok(TokenPair {
+        access_token: encode_claims(secret, email, TokenType::Access, ACCESS_TOKEN_TTL_SECONDS, &Uuid::new_v4().to_string())?,
+        refresh_token: encode_claims(secret, email, TokenType::Refresh, REFRESH_TOKEN_TTL_SECONDS, &refresh_jti)?,
+        refresh_jti,
+    })
+}
+
+/// Mints a new access token for an already-validated refresh token.
+pub fn issue_access_token(secret: &[u8], email: &str) -> Result<String, AuthError> {
+    encode_claims(secret, email, TokenType::Access, ACCESS_TOKEN_TTL_SECONDS, &Uuid::new_v4().to_string())
+}
+
+pub fn decode_claims(secret: &[u8], token: &str) -> Result<Claims, AuthError> {
+    decode::<Claims>(token, &DecodingKey::from_secret(secret), &Validation::default())
+        .map(|data| data.claims)
+        .map_err(|_| AuthError::InvalidToken)
+}
+
+/// Extracts and validates a bearer access token from the `Authorization` header.
+///
+/// Rejects with 401 if the header is missing, malformed, or the token is
+/// expired/invalid/not an access token.
+pub struct AccessClaims(pub Claims);
+
+impl FromRequestParts<Arc<Store>> for AccessClaims {
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<Store>) -> Result<Self, Self::Rejection> {
+        let header = parts
+            .headers
+            .get(http::header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .ok_or(AuthError::MissingToken)?;
+
+        let token = header.strip_prefix("Bearer ").ok_or(AuthError::MissingToken)?;
+        let claims = decode_claims(state.jwt_secret.as_bytes(), token)?;
+
+        if claims.token_type != TokenType::Access {
+            return Err(AuthError::InvalidToken);
+        }
+
+        Ok(AccessClaims(claims))
+    }
+}
+
+/// Identifies the caller regardless of which credential they presented.
+///
+/// Checked in order: an `X-API-Key` header, then a `Bearer` JWT access
+/// token, then the `tower_sessions` cookie session. The first credential
+/// present decides: an invalid API key or bearer token is rejected with 401
+/// without falling back to the session, and with none present and no logged-in
+/// session the request is rejected with 401 too.
+pub struct AuthenticatedUser(pub String);
+
+impl FromRequestParts<Arc<Store>> for AuthenticatedUser {
+    type Rejection = AuthError;
+
+    async fn from_request_parts(parts: &mut Parts, state: &Arc<Store>) -> Result<Self, Self::Rejection> {
+        if let Some(api_key) = parts
+            .headers
+            .get("X-API-Key")
+            .and_then(|value| value.to_str().ok())
+        {
+            let email = state
+                .authenticate_api_key(api_key)
+                .await
+                .map_err(|_| AuthError::InvalidToken)?;
+            return Ok(AuthenticatedUser(email));
+        }
+
+        if let Some(header) = parts
+            .headers
+            .get(http::header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+        {
+            if let Some(token) = header.strip_prefix("Bearer ") {
+                let claims = decode_claims(state.jwt_secret.as_bytes(), token)?;
+                if claims.token_type != TokenType::Access {
+                    return Err(AuthError::InvalidToken);
+                }
+                return Ok(AuthenticatedUser(claims.sub));
+            }
+        }
+
+        let session = Session::from_request_parts(parts, state)
+            .await
+            .map_err(|_| AuthError::MissingToken)?;
+        let email: Option<String> = session
+            .get("user_email")
+            .await
+            .map_err(|_| AuthError::MissingToken)?;
+
+        email.map(AuthenticatedUser).ok_or(AuthError::MissingToken)
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum AuthError {
+    #[error("missing or malformed Authorization header")]
+    MissingToken,
+
+    #[error("invalid or expired token")]
+    InvalidToken,
+
+    #[error("failed to create token")]
+    TokenCreation,
+}
+
+impl axum::response::IntoResponse for AuthError {
+    fn into_response(self) -> axum::response::Response {
+        let body = axum::Json(serde_json::json!({ "error": self.to_string() }));
+        (http::StatusCode::UNAUTHORIZED, body).into_response()
+    }
+}
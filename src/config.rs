@@ -0,0 +1,45 @@
+use thiserror::Error;
+
+/// Centralizes the environment variables the service needs at startup so
+/// invalid configuration surfaces as one readable error instead of a panic
+/// deep inside `Store::new` or the session layer.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub database_url: String,
+    pub jwt_secret: String,
+    pub session_ttl_seconds: i64,
+    pub bind_addr: String,
+    pub max_db_connections: u32,
+}
+
+impl Config {
+    pub fn from_env() -> Result<Self, ConfigError> {
+        Ok(Config {
+            database_url: require_var("DATABASE_URL")?,
+            jwt_secret: require_var("JWT_SECRET")?,
+            session_ttl_seconds: parse_var("SESSION_TTL_SECONDS", 10)?,
+            bind_addr: std::env::var("BIND_ADDR").unwrap_or_else(|_| "0.0.0.0:3000".to_string()),
+            max_db_connections: parse_var("MAX_DB_CONNECTIONS", 5)?,
+        })
+    }
+}
+
+fn require_var(key: &str) -> Result<String, ConfigError> {
+    std::env::var(key).map_err(|_| ConfigError::MissingVar(key.to_string()))
+}
+
+fn parse_var<T: std::str::FromStr>(key: &str, default: T) -> Result<T, ConfigError> {
+    match std::env::var(key) {
+        Ok(value) => value.parse::<T>().map_err(|_| ConfigError::InvalidVar(key.to_string())),
+        Err(_) => Ok(default),
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("missing required environment variable: {0}")]
+    MissingVar(String),
+
+    #[error("invalid value for environment variable: {0}")]
+    InvalidVar(String),
+}
@@ -1,57 +1,102 @@
 use axum::{
     routing::{post, get, put, delete},
     Router,
-    http::StatusCode,
+    http::{StatusCode, header},
     response::IntoResponse,
-    extract::{Json, State, Path},
+    extract::{DefaultBodyLimit, Json, Multipart, State, Path},
 };
-use serde::Deserialize;
+use serde::{Deserialize, Serialize};
 use tower_sessions::{Expiry, SessionManagerLayer, Session, cookie::time::Duration};
 use std::{sync::Arc};
 use sqlx::migrate;
 use tower_sessions_sqlx_store::PostgresStore;
 use validator::Validate;
+use utoipa::{OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
+use saas_boilerplate::{auth, config, store};
 
-mod store;
-mod auth;
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        login,
+        add_user,
+        create_project_handler,
+        list_project_handler,
+        get_project_by_id_handler,
+        update_project_handler,
+        delete_project_handler,
+        upload_avatar_handler,
+        get_avatar_handler,
+        create_api_key_handler,
+        revoke_api_key_handler,
+        health_check,
+    ),
+    components(schemas(
+        LoginRequest,
+        NewUser,
+        TokenResponse,
+        NewApiKeyRequest,
+        NewApiKeyResponse,
+        store::Project,
+        store::UpdateProject,
+        store::ErrorResponse,
+    ))
+)]
+struct ApiDoc;
 
 #[tokio::main]
 async fn main() {
     dotenvy::from_filename(".env").ok();
-    let db_url = std::env::var("DATABASE_URL")
-        .expect("DATABASE_URL must be set");
-    let user_password_db = store::Store::new(&db_url).await;
+    let config = config::Config::from_env().expect("Invalid configuration");
+    let user_password_db = store::Store::new(&config).await;
 
     migrate!()
         .run(&user_password_db.connection)
         .await
         .expect("Failed to run migrations");
-    
+
     let shared_store = Arc::new(user_password_db);
-    
+
     let memory_store = PostgresStore::new(shared_store.connection.clone());
     let session_layer = SessionManagerLayer::new(memory_store)
         .with_secure(false)
-        .with_expiry(Expiry::OnInactivity(Duration::seconds(10)));
+        .with_expiry(Expiry::OnInactivity(Duration::seconds(config.session_ttl_seconds)));
      
 
     let app = Router::new()
         .route("/login", post(login))
         .route("/logout", post(logout))
+        .route("/refresh", post(refresh))
         .route("/signup", post(add_user))
         .route("/projects", post(create_project_handler))
         .route("/projects", get(list_project_handler))
         .route("/projects/{id}", get(get_project_by_id_handler))
         .route("/projects/{id}", put(update_project_handler))
         .route("/projects/{id}", delete(delete_project_handler))
+        .route("/account/avatar", post(upload_avatar_handler).layer(DefaultBodyLimit::max(MAX_AVATAR_BYTES)))
+        .route("/account/avatar", get(get_avatar_handler))
+        .route("/account/api-keys", post(create_api_key_handler))
+        .route("/account/api-keys/{id}", delete(revoke_api_key_handler))
         .route("/healthz", get(health_check))
+        .merge(SwaggerUi::new("/swagger-ui").url("/api-docs/openapi.json", ApiDoc::openapi()))
         .with_state(shared_store)
         .layer(session_layer);
 
-    let listener = tokio::net::TcpListener::bind("0.0.0.0:3000").await.unwrap();
+    let listener = tokio::net::TcpListener::bind(&config.bind_addr).await.unwrap();
     axum::serve(listener, app).await.unwrap();
 }
 
+/// Authenticate with an email/password and receive a session cookie plus a JWT token pair.
+#[utoipa::path(
+    post,
+    path = "/login",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Login successful", body = TokenResponse),
+        (status = 401, description = "Unknown user or incorrect password", body = store::ErrorResponse),
+        (status = 500, description = "Database or session error", body = store::ErrorResponse),
+    )
+)]
 async fn login(
     State(store): State<Arc<store::Store>>,
     session: Session,
@@ -62,18 +107,65 @@ async fn login(
 
     session.insert("user_email", &payload.email).await
         .map_err(|_| store::StoreError::SessionError)?;
-    Ok(StatusCode::OK)
+
+    let tokens = auth::issue_token_pair(store.jwt_secret.as_bytes(), &payload.email)
+        .map_err(|_| store::StoreError::SessionError)?;
+    store.store_refresh_token(&payload.email, &tokens.refresh_jti, &tokens.refresh_token).await?;
+
+    Ok(Json(TokenResponse {
+        access_token: tokens.access_token,
+        refresh_token: tokens.refresh_token,
+    }))
 }
 
+/// Revokes the caller's refresh tokens and, if present, clears their cookie
+/// session. The caller can be identified by a session cookie, a bearer JWT
+/// access token, or an API key, so non-browser clients can log out too.
 async fn logout(
-    session: Session,
+    State(store): State<Arc<store::Store>>,
+    auth::AuthenticatedUser(email): auth::AuthenticatedUser,
     _rate_limit: auth::RateLimit,
+    session: Session,
 ) -> Result<impl IntoResponse, store::StoreError> {
+    store.revoke_refresh_tokens(&email).await?;
     session.clear().await;
 
     Ok(StatusCode::OK)
 }
 
+async fn refresh(
+    State(store): State<Arc<store::Store>>,
+    Json(payload): Json<RefreshRequest>,
+) -> Result<impl IntoResponse, store::StoreError> {
+    let claims = auth::decode_claims(store.jwt_secret.as_bytes(), &payload.refresh_token)
+        .map_err(|_| store::StoreError::InvalidToken)?;
+
+    if claims.token_type != auth::TokenType::Refresh {
+        return Err(store::StoreError::InvalidToken);
+    }
+
+    if !store.is_refresh_token_active(&claims.sub, &claims.jti, &payload.refresh_token).await? {
+        return Err(store::StoreError::InvalidToken);
+    }
+
+    let access_token = auth::issue_access_token(store.jwt_secret.as_bytes(), &claims.sub)
+        .map_err(|_| store::StoreError::SessionError)?;
+
+    Ok(Json(AccessTokenResponse { access_token }))
+}
+
+/// Register a new account.
+#[utoipa::path(
+    post,
+    path = "/signup",
+    request_body = NewUser,
+    responses(
+        (status = 201, description = "Account created"),
+        (status = 400, description = "Invalid email or password", body = store::ErrorResponse),
+        (status = 409, description = "A user with that email already exists", body = store::ErrorResponse),
+        (status = 500, description = "Database error", body = store::ErrorResponse),
+    )
+)]
 async fn add_user(
     State(store): State<Arc<store::Store>>,
     Json(payload): Json<NewUser>,
@@ -91,69 +183,266 @@ async fn add_user(
     }
 }
 
+/// Create a project owned by the authenticated user.
+#[utoipa::path(
+    post,
+    path = "/projects",
+    request_body = store::Project,
+    responses(
+        (status = 201, description = "Project created"),
+        (status = 401, description = "Missing or invalid session, access token, or API key"),
+        (status = 400, description = "Failed to create project", body = store::ErrorResponse),
+    )
+)]
 async fn create_project_handler(
     State(store): State<Arc<store::Store>>,
-    session: Session,
+    auth::AuthenticatedUser(owner): auth::AuthenticatedUser,
     _rate_limit: auth::RateLimit,
-    Json(project): Json<store::Project>,
+    Json(mut project): Json<store::Project>,
 ) -> Result<StatusCode, store::StoreError> {
-    require_login(&session).await?;
+    project.user_email = owner;
     store.create_project(project)
         .await
         .map(|_| StatusCode::CREATED)
         .map_err(|_| store::StoreError::FailedProjectCreation)
 }
 
+/// List the projects owned by the authenticated user.
+#[utoipa::path(
+    get,
+    path = "/projects",
+    responses(
+        (status = 200, description = "Projects owned by the caller", body = [store::Project]),
+        (status = 401, description = "Missing or invalid session, access token, or API key", body = store::ErrorResponse),
+    )
+)]
 async fn list_project_handler(
     State(store): State<Arc<store::Store>>,
-    session: Session,
+    auth::AuthenticatedUser(owner): auth::AuthenticatedUser,
     _rate_limit: auth::RateLimit,
-) -> Result<StatusCode, store::StoreError> {
-    require_login(&session).await?;
-    store.list_projects()
+) -> Result<Json<Vec<store::Project>>, store::StoreError> {
+    store.list_projects(&owner)
         .await
-        .map(|_| StatusCode::OK)
-        .map_err(|_| store::StoreError::ProjectNotFound)
+        .map(Json)
 }
 
+/// Fetch a single project owned by the authenticated user.
+#[utoipa::path(
+    get,
+    path = "/projects/{id}",
+    params(("id" = uuid::Uuid, Path, description = "Project id")),
+    responses(
+        (status = 200, description = "The requested project", body = store::Project),
+        (status = 401, description = "Missing or invalid session, access token, or API key", body = store::ErrorResponse),
+        (status = 403, description = "Project is owned by another user", body = store::ErrorResponse),
+        (status = 404, description = "Project not found", body = store::ErrorResponse),
+    )
+)]
 async fn get_project_by_id_handler(
     State(store): State<Arc<store::Store>>,
-    session: Session,
+    auth::AuthenticatedUser(owner): auth::AuthenticatedUser,
     _rate_limit: auth::RateLimit,
     Path(id): Path<uuid::Uuid>,
-) -> Result<StatusCode, store::StoreError> {
-    require_login(&session).await?;
-    store.get_project_by_id(id)
+) -> Result<Json<store::Project>, store::StoreError> {
+    store.get_project_by_id(id, &owner)
         .await
-        .map(|_| StatusCode::OK)
-        .map_err(|_| store::StoreError::ProjectNotFound)
+        .map(Json)
 }
 
+/// Update a project owned by the authenticated user.
+#[utoipa::path(
+    put,
+    path = "/projects/{id}",
+    params(("id" = uuid::Uuid, Path, description = "Project id")),
+    request_body = store::UpdateProject,
+    responses(
+        (status = 200, description = "The updated project", body = store::Project),
+        (status = 401, description = "Missing or invalid session, access token, or API key", body = store::ErrorResponse),
+        (status = 403, description = "Project is owned by another user", body = store::ErrorResponse),
+        (status = 404, description = "Project not found", body = store::ErrorResponse),
+    )
+)]
 async fn update_project_handler(
     State(store): State<Arc<store::Store>>,
-    session: Session,
+    auth::AuthenticatedUser(owner): auth::AuthenticatedUser,
     _rate_limit: auth::RateLimit,
     Path(id): Path<uuid::Uuid>,
     Json(update): Json<store::UpdateProject>
 ) -> Result<Json<store::Project>, store::StoreError> {
-    require_login(&session).await?;
-    store.update_project(update, id)
+    store.update_project(update, id, &owner)
         .await
         .map(Json)
 }
 
+/// Delete a project owned by the authenticated user.
+#[utoipa::path(
+    delete,
+    path = "/projects/{id}",
+    params(("id" = uuid::Uuid, Path, description = "Project id")),
+    responses(
+        (status = 200, description = "Project deleted"),
+        (status = 401, description = "Missing or invalid session, access token, or API key", body = store::ErrorResponse),
+        (status = 403, description = "Project is owned by another user", body = store::ErrorResponse),
+        (status = 404, description = "Project not found", body = store::ErrorResponse),
+    )
+)]
 async fn delete_project_handler(
+    State(store): State<Arc<store::Store>>,
+    auth::AuthenticatedUser(owner): auth::AuthenticatedUser,
+    _rate_limit: auth::RateLimit,
+    Path(id): Path<uuid::Uuid>,
+) -> Result<StatusCode, store::StoreError> {
+    store.delete_project(id, &owner)
+        .await
+        .map(|_| StatusCode::OK)
+}
+
+/// Avatars are re-encoded to a PNG no larger than this on each side; the
+/// aspect ratio is kept, so only square uploads give a square thumbnail.
+const AVATAR_THUMBNAIL_SIZE: u32 = 256;
+/// Reject uploads larger than this before we even try to decode them.
+const MAX_AVATAR_BYTES: usize = 5 * 1024 * 1024;
+/// Reject images whose decoded dimensions exceed this on either side, so a
+/// small, highly-compressed upload can't balloon into a huge in-memory bitmap.
+const MAX_AVATAR_DIMENSION: u32 = 8192;
+
+/// Upload an avatar image for the authenticated user. The image is validated,
+/// decoded, and re-encoded as a normalized PNG thumbnail before being stored.
+#[utoipa::path(
+    post,
+    path = "/account/avatar",
+    responses(
+        (status = 200, description = "Avatar updated"),
+        (status = 400, description = "Upload was not a valid image or exceeded the size limit", body = store::ErrorResponse),
+        (status = 401, description = "Not logged in", body = store::ErrorResponse),
+    )
+)]
+async fn upload_avatar_handler(
+    State(store): State<Arc<store::Store>>,
+    session: Session,
+    _rate_limit: auth::RateLimit,
+    mut multipart: Multipart,
+) -> Result<StatusCode, store::StoreError> {
+    let owner = require_login(&session).await?;
+
+    let field = multipart.next_field().await
+        .map_err(|_| store::StoreError::InvalidInput("malformed multipart body".to_string()))?
+        .ok_or_else(|| store::StoreError::InvalidInput("missing avatar file field".to_string()))?;
+
+    let content_type = field.content_type().unwrap_or("application/octet-stream").to_string();
+    if !content_type.starts_with("image/") {
+        return Err(store::StoreError::InvalidInput("uploaded file is not an image".to_string()));
+    }
+
+    let data = field.bytes().await
+        .map_err(|_| store::StoreError::InvalidInput("failed to read upload".to_string()))?;
+
+    if data.len() > MAX_AVATAR_BYTES {
+        return Err(store::StoreError::InvalidInput("avatar exceeds the upload size limit".to_string()));
+    }
+
+    let mut reader = image::ImageReader::new(std::io::Cursor::new(&data))
+        .with_guessed_format()
+        .map_err(|_| store::StoreError::InvalidInput("uploaded file is not a valid image".to_string()))?;
+
+    let mut limits = image::Limits::default();
+    limits.max_image_width = Some(MAX_AVATAR_DIMENSION);
+    limits.max_image_height = Some(MAX_AVATAR_DIMENSION);
+    reader.limits(limits);
+
+    let decoded = reader.decode()
+        .map_err(|_| store::StoreError::InvalidInput("uploaded file is not a valid image, or exceeds the maximum dimensions".to_string()))?;
+
+    let thumbnail = decoded.thumbnail(AVATAR_THUMBNAIL_SIZE, AVATAR_THUMBNAIL_SIZE);
+
+    let mut png_bytes = Vec::new();
+    thumbnail
+        .write_to(&mut std::io::Cursor::new(&mut png_bytes), image::ImageFormat::Png)
+        .map_err(|_| store::StoreError::InvalidInput("failed to encode avatar thumbnail".to_string()))?;
+
+    store.upsert_avatar(&owner, "image/png", png_bytes).await?;
+
+    Ok(StatusCode::OK)
+}
+
+/// Fetch the authenticated user's avatar thumbnail.
+#[utoipa::path(
+    get,
+    path = "/account/avatar",
+    responses(
+        (status = 200, description = "Avatar image bytes"),
+        (status = 401, description = "Not logged in", body = store::ErrorResponse),
+        (status = 404, description = "No avatar has been uploaded", body = store::ErrorResponse),
+    )
+)]
+async fn get_avatar_handler(
+    State(store): State<Arc<store::Store>>,
+    session: Session,
+    _rate_limit: auth::RateLimit,
+) -> Result<impl IntoResponse, store::StoreError> {
+    let owner = require_login(&session).await?;
+    let (content_type, bytes) = store.get_avatar(&owner).await?;
+
+    Ok(([(header::CONTENT_TYPE, content_type)], bytes))
+}
+
+/// Mint a long-lived API key for the logged-in user. The raw key is returned
+/// exactly once; only its non-secret key id and the hash of its secret half
+/// are persisted, so it cannot be recovered later.
+#[utoipa::path(
+    post,
+    path = "/account/api-keys",
+    request_body = NewApiKeyRequest,
+    responses(
+        (status = 201, description = "API key created", body = NewApiKeyResponse),
+        (status = 401, description = "Not logged in", body = store::ErrorResponse),
+    )
+)]
+async fn create_api_key_handler(
+    State(store): State<Arc<store::Store>>,
+    session: Session,
+    _rate_limit: auth::RateLimit,
+    Json(payload): Json<NewApiKeyRequest>,
+) -> Result<(StatusCode, Json<NewApiKeyResponse>), store::StoreError> {
+    let owner = require_login(&session).await?;
+    let key = store::Store::generate_api_key();
+    let id = store.create_api_key(&owner, payload.label.as_deref(), &key).await?;
+
+    Ok((StatusCode::CREATED, Json(NewApiKeyResponse { id, api_key: key })))
+}
+
+/// Revoke an API key owned by the logged-in user.
+#[utoipa::path(
+    delete,
+    path = "/account/api-keys/{id}",
+    params(("id" = uuid::Uuid, Path, description = "API key id")),
+    responses(
+        (status = 200, description = "API key revoked"),
+        (status = 401, description = "Not logged in", body = store::ErrorResponse),
+        (status = 404, description = "API key not found", body = store::ErrorResponse),
+    )
+)]
+async fn revoke_api_key_handler(
     State(store): State<Arc<store::Store>>,
     session: Session,
     _rate_limit: auth::RateLimit,
     Path(id): Path<uuid::Uuid>,
 ) -> Result<StatusCode, store::StoreError> {
-    require_login(&session).await?;
-    store.delete_project(id)
+    let owner = require_login(&session).await?;
+    store.revoke_api_key(id, &owner)
         .await
         .map(|_| StatusCode::OK)
 }
 
+/// Checks connectivity to the database.
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    responses(
+        (status = 200, description = "The service can reach its database"),
+        (status = 503, description = "The database is unreachable"),
+    )
+)]
 async fn health_check(State(store): State<Arc<store::Store>>) -> impl IntoResponse {
     if let Err(e) = sqlx::query("SELECT 1").execute(&store.connection).await {
         eprintln!("Health check DB error: {:?}", e);
@@ -169,19 +458,46 @@ async fn require_login(session: &Session) -> Result<String, store::StoreError> {
         .ok_or(store::StoreError::IncorrectPassword)
 }
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 struct LoginRequest {
     email: String,
     password: String,
 }
 
-#[derive(Deserialize, Validate)]
+#[derive(Deserialize)]
+struct RefreshRequest {
+    refresh_token: String,
+}
+
+#[derive(Serialize, ToSchema)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: String,
+}
+
+#[derive(Serialize)]
+struct AccessTokenResponse {
+    access_token: String,
+}
+
+#[derive(Deserialize, Validate, ToSchema)]
 struct NewUser {
     #[validate(email)]
     email: String,
-    
+
     #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
     password: String,
 }
 
+#[derive(Deserialize, ToSchema)]
+struct NewApiKeyRequest {
+    label: Option<String>,
+}
+
+#[derive(Serialize, ToSchema)]
+struct NewApiKeyResponse {
+    id: uuid::Uuid,
+    api_key: String,
+}
+
 
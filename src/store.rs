@@ -4,11 +4,12 @@ use sqlx::{
 use serde::{Serialize, Deserialize};
 use argon2::{
     password_hash::{
-        rand_core::OsRng,
+        rand_core::{OsRng, RngCore},
         PasswordHash, PasswordHasher, PasswordVerifier, SaltString, Error
     },
     Argon2
 };
+use base64::{engine::general_purpose, Engine as _};
 use thiserror::Error;
 use sqlx::Row;
 use axum::{
@@ -17,10 +18,13 @@ use axum::{
     Json,
 };
 use uuid::Uuid;
+use utoipa::ToSchema;
+use crate::config::Config;
 
 #[derive(Debug, Clone)]
 pub struct Store {
     pub connection: PgPool,
+    pub jwt_secret: String,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -29,7 +33,7 @@ pub struct Account {
     pub password: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, ToSchema)]
 pub struct Project {
     pub id: Option<uuid::Uuid>,
     pub user_email: String,
@@ -39,7 +43,7 @@ pub struct Project {
     pub last_updated: Option<chrono::NaiveDateTime>,
 }
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct UpdateProject {
     pub name: Option<String>,
     pub description: Option<String>,
@@ -47,10 +51,10 @@ pub struct UpdateProject {
 }
 
 impl Store {
-    pub async fn new(db_url: &str) -> Self {
+    pub async fn new(config: &Config) -> Self {
         let db_pool = match PgPoolOptions::new()
-            .max_connections(5)
-            .connect(db_url)
+            .max_connections(config.max_db_connections)
+            .connect(&config.database_url)
             .await
             {
                 Ok(pool) => pool,
@@ -58,6 +62,7 @@ impl Store {
             };
         Store {
             connection: db_pool,
+            jwt_secret: config.jwt_secret.clone(),
         }
     }
 
@@ -90,6 +95,181 @@ impl Store {
             }
     }
 
+    /// Persists a refresh token for `email` under its `jti`, storing only its hash.
+    pub async fn store_refresh_token(&self, email: &str, jti: &str, token: &str) -> Result<(), StoreError> {
+        let token_hash = Self::hash_password(token.as_bytes())
+            .map_err(|e| StoreError::HashError(e.to_string()))?;
+
+        sqlx::query(
+            "INSERT INTO refresh_tokens (user_email, token_jti, token_hash) VALUES ($1, $2, $3)",
+        )
+        .bind(email)
+        .bind(jti)
+        .bind(&token_hash)
+        .execute(&self.connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Checks whether `token` matches the non-revoked refresh token hash stored
+    /// for `email` under `jti`. Looks up the single row by its indexed `jti`
+    /// instead of verifying against every refresh token ever issued to the user.
+    pub async fn is_refresh_token_active(&self, email: &str, jti: &str, token: &str) -> Result<bool, StoreError> {
+        let row = sqlx::query(
+            "SELECT token_hash FROM refresh_tokens WHERE user_email = $1 AND token_jti = $2 AND revoked = FALSE",
+        )
+        .bind(email)
+        .bind(jti)
+        .fetch_optional(&self.connection)
+        .await?;
+
+        let Some(row) = row else {
+            return Ok(false);
+        };
+
+        let token_hash: String = row.try_get("token_hash")
+            .map_err(|_| StoreError::UserDataNotFound("token_hash".to_string()))?;
+
+        Ok(Self::verify_hash(token.as_bytes(), &token_hash))
+    }
+
+    /// Revokes every refresh token issued to `email`, e.g. on logout.
+    pub async fn revoke_refresh_tokens(&self, email: &str) -> Result<(), StoreError> {
+        sqlx::query("UPDATE refresh_tokens SET revoked = TRUE WHERE user_email = $1")
+            .bind(email)
+            .execute(&self.connection)
+            .await?;
+
+        Ok(())
+    }
+
+    /// Stores (or replaces) the avatar thumbnail for `email`.
+    pub async fn upsert_avatar(&self, email: &str, content_type: &str, bytes: Vec<u8>) -> Result<(), StoreError> {
+        sqlx::query(
+            r#"
+            INSERT INTO avatars (user_email, content_type, bytes, updated_at)
+            VALUES ($1, $2, $3, NOW())
+            ON CONFLICT (user_email) DO UPDATE
+            SET content_type = EXCLUDED.content_type, bytes = EXCLUDED.bytes, updated_at = NOW()
+            "#,
+        )
+        .bind(email)
+        .bind(content_type)
+        .bind(bytes)
+        .execute(&self.connection)
+        .await?;
+
+        Ok(())
+    }
+
+    /// Generates a fresh, high-entropy API key of the form `{key_id}.{secret}`.
+    /// `key_id` is an indexable, non-secret lookup value; only the secret half
+    /// needs to be kept confidential, and only its hash is ever persisted.
+    /// The caller is shown the full key exactly once.
+    pub fn generate_api_key() -> String {
+        let mut id_bytes = [0u8; 12];
+        OsRng.fill_bytes(&mut id_bytes);
+        let key_id = general_purpose::URL_SAFE_NO_PAD.encode(id_bytes);
+
+        let mut secret_bytes = [0u8; 32];
+        OsRng.fill_bytes(&mut secret_bytes);
+        let secret = general_purpose::URL_SAFE_NO_PAD.encode(secret_bytes);
+
+        format!("{key_id}.{secret}")
+    }
+
+    pub async fn create_api_key(&self, email: &str, label: Option<&str>, key: &str) -> Result<Uuid, StoreError> {
+        let (key_id, secret) = key.split_once('.')
+            .ok_or_else(|| StoreError::InvalidInput("malformed API key".to_string()))?;
+
+        let id = Uuid::new_v4();
+        let key_hash = Self::hash_password(secret.as_bytes())
+            .map_err(|e| StoreError::HashError(e.to_string()))?;
+
+        sqlx::query(
+            "INSERT INTO api_keys (id, user_email, label, key_id, key_hash) VALUES ($1, $2, $3, $4, $5)",
+        )
+        .bind(id)
+        .bind(email)
+        .bind(label)
+        .bind(key_id)
+        .bind(&key_hash)
+        .execute(&self.connection)
+        .await?;
+
+        Ok(id)
+    }
+
+    /// Verifies a presented API key by looking up its `key_id` half (an
+    /// indexed column) and checking the secret half against only that row's
+    /// hash, rather than verifying against every issued key. On a match,
+    /// records the usage and returns the owning user's email.
+    pub async fn authenticate_api_key(&self, presented_key: &str) -> Result<String, StoreError> {
+        let (key_id, secret) = presented_key.split_once('.')
+            .ok_or(StoreError::InvalidToken)?;
+
+        let row = sqlx::query("SELECT id, user_email, key_hash FROM api_keys WHERE key_id = $1")
+            .bind(key_id)
+            .fetch_one(&self.connection)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => StoreError::InvalidToken,
+                other => StoreError::SqlxError(other),
+            })?;
+
+        let key_hash: String = row.try_get("key_hash")
+            .map_err(|_| StoreError::UserDataNotFound("key_hash".to_string()))?;
+
+        if !Self::verify_hash(secret.as_bytes(), &key_hash) {
+            return Err(StoreError::InvalidToken);
+        }
+
+        let id: Uuid = row.try_get("id")
+            .map_err(|_| StoreError::UserDataNotFound("id".to_string()))?;
+        let user_email: String = row.try_get("user_email")
+            .map_err(|_| StoreError::UserDataNotFound("user_email".to_string()))?;
+
+        sqlx::query("UPDATE api_keys SET last_used_at = NOW() WHERE id = $1")
+            .bind(id)
+            .execute(&self.connection)
+            .await?;
+
+        Ok(user_email)
+    }
+
+    pub async fn revoke_api_key(&self, id: Uuid, owner: &str) -> Result<(), StoreError> {
+        let result = sqlx::query("DELETE FROM api_keys WHERE id = $1 AND user_email = $2")
+            .bind(id)
+            .bind(owner)
+            .execute(&self.connection)
+            .await?;
+
+        if result.rows_affected() == 0 {
+            return Err(StoreError::ApiKeyNotFound);
+        }
+
+        Ok(())
+    }
+
+    pub async fn get_avatar(&self, email: &str) -> Result<(String, Vec<u8>), StoreError> {
+        let row = sqlx::query("SELECT content_type, bytes FROM avatars WHERE user_email = $1")
+            .bind(email)
+            .fetch_one(&self.connection)
+            .await
+            .map_err(|e| match e {
+                sqlx::Error::RowNotFound => StoreError::AvatarNotFound,
+                other => StoreError::SqlxError(other),
+            })?;
+
+        let content_type: String = row.try_get("content_type")
+            .map_err(|_| StoreError::UserDataNotFound("content_type".to_string()))?;
+        let bytes: Vec<u8> = row.try_get("bytes")
+            .map_err(|_| StoreError::UserDataNotFound("bytes".to_string()))?;
+
+        Ok((content_type, bytes))
+    }
+
     pub async fn register_user (&self, username: &str, password: &str) -> Result<(), StoreError> {
         let password = password.as_bytes();
         let hashed_password = Self::hash_password(password)
@@ -100,17 +280,44 @@ impl Store {
         .bind(username)
         .bind(&hashed_password)
         .execute(&self.connection)
-        .await?;
+        .await
+        .map_err(Self::map_insert_error)?;
 
     Ok(())
     }
 
+    /// Turns a unique-violation on the accounts email constraint into a
+    /// `StoreError::UserExists` instead of letting it bubble up as an opaque 500.
+    fn map_insert_error(e: sqlx::Error) -> StoreError {
+        if let sqlx::Error::Database(db_err) = &e {
+            let is_duplicate_account = db_err.is_unique_violation()
+                && db_err.constraint() == Some("accounts_username_key");
+
+            if is_duplicate_account {
+                return StoreError::UserExists;
+            }
+        }
+
+        StoreError::SqlxError(e)
+    }
+
     fn hash_password(password: &[u8]) -> Result<String, Error> {
         let salt = SaltString::generate(&mut OsRng);
         let hashed_password = Argon2::default().hash_password(password, &salt)?.to_string();
         Ok(hashed_password)
     }
 
+    /// Verifies `secret` against a previously-hashed value, returning `false`
+    /// rather than erroring on a malformed hash so callers can treat it as a mismatch.
+    fn verify_hash(secret: &[u8], hashed: &str) -> bool {
+        let parsed_hash = match PasswordHash::new(hashed) {
+            Ok(ph) => ph,
+            Err(_) => return false,
+        };
+
+        Argon2::default().verify_password(secret, &parsed_hash).is_ok()
+    }
+
     pub async fn create_project(&self, project: Project) -> Result<(), StoreError> {
         let id = Uuid::new_v4();
         sqlx::query(
@@ -127,10 +334,11 @@ impl Store {
     Ok(())
     }
 
-    pub async fn list_projects(&self) -> Result<Vec<Project>, StoreError> {
+    pub async fn list_projects(&self, owner: &str) -> Result<Vec<Project>, StoreError> {
         let projects = sqlx::query_as!(
             Project,
-            "SELECT id, user_email, name, description, created_at, last_updated FROM projects"
+            "SELECT id, user_email, name, description, created_at, last_updated FROM projects WHERE user_email = $1",
+            owner
         )
         .fetch_all(&self.connection)
         .await?;
@@ -139,7 +347,7 @@ impl Store {
     }
 
     pub async fn get_project_by_id(
-        &self, id: uuid::Uuid
+        &self, id: uuid::Uuid, owner: &str
     ) -> Result<Project, StoreError> {
         let project = sqlx::query_as!(
             Project,
@@ -151,18 +359,28 @@ impl Store {
             id
         )
         .fetch_one(&self.connection)
-        .await?;
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::RowNotFound => StoreError::ProjectNotFound,
+            other => StoreError::SqlxError(other),
+        })?;
+
+        if project.user_email != owner {
+            return Err(StoreError::Forbidden);
+        }
 
         Ok(project)
     }
-    
-    pub async fn update_project (&self, update: UpdateProject, id: uuid::Uuid)
+
+    pub async fn update_project (&self, update: UpdateProject, id: uuid::Uuid, owner: &str)
     -> Result<Project, StoreError> {
+        self.get_project_by_id(id, owner).await?;
+
         let project = sqlx::query_as!(
             Project,
-            r#"UPDATE projects SET name = $1, description = $2, last_updated = $3 WHERE id = $4
+            r#"UPDATE projects SET name = $1, description = $2, last_updated = $3 WHERE id = $4 AND user_email = $5
             RETURNING id, user_email, name, description, created_at, last_updated"#,
-            update.name, update.description, update.update_timestamp, id
+            update.name, update.description, update.update_timestamp, id, owner
         )
         .fetch_one(&self.connection)
         .await?;
@@ -170,10 +388,13 @@ impl Store {
         Ok(project)
     }
 
-    pub async fn delete_project(&self, id: uuid::Uuid)
+    pub async fn delete_project(&self, id: uuid::Uuid, owner: &str)
     -> Result<(), StoreError> {
-        let result = sqlx::query("DELETE FROM projects WHERE id = $1")
+        self.get_project_by_id(id, owner).await?;
+
+        let result = sqlx::query("DELETE FROM projects WHERE id = $1 AND user_email = $2")
             .bind(id)
+            .bind(owner)
             .execute(&self.connection)
             .await?;
 
@@ -211,10 +432,31 @@ pub enum StoreError {
     #[error("Failed to create project")]
     FailedProjectCreation,
 
+    #[error("Session error")]
+    SessionError,
+
+    #[error("Invalid or expired token")]
+    InvalidToken,
+
+    #[error("You do not have access to this project")]
+    Forbidden,
+
+    #[error("A user with that email already exists")]
+    UserExists,
+
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+
+    #[error("No avatar has been uploaded for this user")]
+    AvatarNotFound,
+
+    #[error("API key not found")]
+    ApiKeyNotFound,
+
 }
 
-#[derive(Serialize)]
-struct ErrorResponse {
+#[derive(Serialize, ToSchema)]
+pub struct ErrorResponse {
     error: String,
 }
 
@@ -222,8 +464,13 @@ impl IntoResponse for StoreError {
     fn into_response(self) -> Response {
         let status = match self {
             StoreError::UserNotFound | StoreError::IncorrectPassword => StatusCode::UNAUTHORIZED,
-            StoreError::UserDataNotFound(_) | StoreError::ProjectNotFound | StoreError::MalformedStoreHash | StoreError::FailedProjectCreation => StatusCode::BAD_REQUEST,
-            StoreError::HashError(_) | StoreError::SqlxError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+            StoreError::UserDataNotFound(_) | StoreError::MalformedStoreHash | StoreError::FailedProjectCreation => StatusCode::BAD_REQUEST,
+            StoreError::InvalidToken => StatusCode::UNAUTHORIZED,
+            StoreError::Forbidden => StatusCode::FORBIDDEN,
+            StoreError::UserExists => StatusCode::CONFLICT,
+            StoreError::InvalidInput(_) => StatusCode::BAD_REQUEST,
+            StoreError::AvatarNotFound | StoreError::ApiKeyNotFound | StoreError::ProjectNotFound => StatusCode::NOT_FOUND,
+            StoreError::HashError(_) | StoreError::SqlxError(_) | StoreError::SessionError => StatusCode::INTERNAL_SERVER_ERROR,
         };
 
         let body = Json(ErrorResponse {
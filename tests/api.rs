@@ -16,7 +16,9 @@ async fn health_check_works() {
 async fn signup_and_login_flow() {
     
     // Clear duplicate test emails from database
-    let db_pool = sqlx::PgPool::connect(&dotenvy::var("DATABASE_URL").unwrap()).await.unwrap();
+    dotenvy::from_filename(".env").ok();
+    let config = saas_boilerplate::config::Config::from_env().expect("invalid configuration");
+    let db_pool = sqlx::PgPool::connect(&config.database_url).await.unwrap();
     sqlx::query!("DELETE FROM accounts WHERE email = $1", "test@example.com")
     .execute(&db_pool)
     .await
@@ -61,3 +63,130 @@ async fn project_access_requires_auth() {
 
     assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
 }
+
+#[tokio::test]
+async fn project_ownership_is_enforced_across_users() {
+    // Clear duplicate test emails/projects from database
+    dotenvy::from_filename(".env").ok();
+    let config = saas_boilerplate::config::Config::from_env().expect("invalid configuration");
+    let db_pool = sqlx::PgPool::connect(&config.database_url).await.unwrap();
+    sqlx::query!(
+        "DELETE FROM accounts WHERE email IN ($1, $2)",
+        "owner@example.com",
+        "intruder@example.com"
+    )
+    .execute(&db_pool)
+    .await
+    .unwrap();
+    sqlx::query!(
+        "DELETE FROM projects WHERE user_email IN ($1, $2)",
+        "owner@example.com",
+        "intruder@example.com"
+    )
+    .execute(&db_pool)
+    .await
+    .unwrap();
+
+    let owner = reqwest::Client::builder().cookie_store(true).build().unwrap();
+    owner.post("http://localhost:3000/signup")
+        .json(&serde_json::json!({"email": "owner@example.com", "password": "password123"}))
+        .send()
+        .await
+        .expect("Signup request failed");
+    owner.post("http://localhost:3000/login")
+        .json(&serde_json::json!({"email": "owner@example.com", "password": "password123"}))
+        .send()
+        .await
+        .expect("Login request failed");
+
+    let create_res = owner.post("http://localhost:3000/projects")
+        .json(&serde_json::json!({"user_email": "owner@example.com", "name": "Owner's project", "description": null}))
+        .send()
+        .await
+        .expect("Create project request failed");
+    assert_eq!(create_res.status(), reqwest::StatusCode::CREATED);
+
+    let owner_projects: serde_json::Value = owner.get("http://localhost:3000/projects")
+        .send()
+        .await
+        .expect("List projects request failed")
+        .json()
+        .await
+        .expect("Failed to parse project list");
+    let project_id = owner_projects[0]["id"].as_str().expect("project id missing").to_string();
+
+    let intruder = reqwest::Client::builder().cookie_store(true).build().unwrap();
+    intruder.post("http://localhost:3000/signup")
+        .json(&serde_json::json!({"email": "intruder@example.com", "password": "password123"}))
+        .send()
+        .await
+        .expect("Signup request failed");
+    intruder.post("http://localhost:3000/login")
+        .json(&serde_json::json!({"email": "intruder@example.com", "password": "password123"}))
+        .send()
+        .await
+        .expect("Login request failed");
+
+    let get_res = intruder.get(&format!("http://localhost:3000/projects/{project_id}"))
+        .send()
+        .await
+        .expect("Get project request failed");
+    assert_eq!(get_res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let update_res = intruder.put(&format!("http://localhost:3000/projects/{project_id}"))
+        .json(&serde_json::json!({"name": "Hijacked", "description": null, "update_timestamp": "2026-01-01T00:00:00"}))
+        .send()
+        .await
+        .expect("Update project request failed");
+    assert_eq!(update_res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let delete_res = intruder.delete(&format!("http://localhost:3000/projects/{project_id}"))
+        .send()
+        .await
+        .expect("Delete project request failed");
+    assert_eq!(delete_res.status(), reqwest::StatusCode::FORBIDDEN);
+}
+
+#[tokio::test]
+async fn missing_project_returns_not_found() {
+    dotenvy::from_filename(".env").ok();
+    let config = saas_boilerplate::config::Config::from_env().expect("invalid configuration");
+    let db_pool = sqlx::PgPool::connect(&config.database_url).await.unwrap();
+    sqlx::query!("DELETE FROM accounts WHERE email = $1", "missing-project@example.com")
+        .execute(&db_pool)
+        .await
+        .unwrap();
+
+    let client = reqwest::Client::builder().cookie_store(true).build().unwrap();
+    client.post("http://localhost:3000/signup")
+        .json(&serde_json::json!({"email": "missing-project@example.com", "password": "password123"}))
+        .send()
+        .await
+        .expect("Signup request failed");
+    client.post("http://localhost:3000/login")
+        .json(&serde_json::json!({"email": "missing-project@example.com", "password": "password123"}))
+        .send()
+        .await
+        .expect("Login request failed");
+
+    let nonexistent_id = uuid::Uuid::new_v4();
+
+    let get_res = client.get(&format!("http://localhost:3000/projects/{nonexistent_id}"))
+        .send()
+        .await
+        .expect("Get project request failed");
+    assert_eq!(get_res.status(), reqwest::StatusCode::NOT_FOUND);
+
+    let update_res = client.put(&format!("http://localhost:3000/projects/{nonexistent_id}"))
+        .json(&serde_json::json!({"name": "Ghost", "description": null, "update_timestamp": "2026-01-01T00:00:00"}))
+        .send()
+        .await
+        .expect("Update project request failed");
+    assert_eq!(update_res.status(), reqwest::StatusCode::NOT_FOUND);
+
+    let delete_res = client.delete(&format!("http://localhost:3000/projects/{nonexistent_id}"))
+        .send()
+        .await
+        .expect("Delete project request failed");
+    assert_eq!(delete_res.status(), reqwest::StatusCode::NOT_FOUND);
+}